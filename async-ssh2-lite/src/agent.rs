@@ -0,0 +1,160 @@
+use std::io;
+use std::sync::Arc;
+
+use ssh2::{Agent, Session};
+
+use crate::session_stream::AsyncSessionStream;
+
+/// An owned snapshot of one identity held by the ssh-agent.
+///
+/// `ssh2::Agent::identities()` hands back references into the agent's own
+/// identity list, which can't be held across an `.await` point. We copy out
+/// just enough to re-find the matching raw identity later (its public key
+/// blob, which is unique per identity) and to let callers inspect the
+/// comment without going back to the agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentIdentity {
+    blob: Vec<u8>,
+    comment: String,
+}
+
+impl AgentIdentity {
+    pub fn blob(&self) -> &[u8] {
+        &self.blob
+    }
+
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+}
+
+pub struct AsyncAgent<S> {
+    inner: Agent,
+    session: Session,
+    stream: Arc<S>,
+    identities: Vec<AgentIdentity>,
+}
+
+impl<S> AsyncAgent<S> {
+    pub(crate) fn from_parts(inner: Agent, session: Session, stream: Arc<S>) -> Self {
+        Self {
+            inner,
+            session,
+            stream,
+            identities: Vec::new(),
+        }
+    }
+}
+
+impl<S> AsyncAgent<S>
+where
+    S: AsyncSessionStream + Send + Sync,
+{
+    pub async fn connect(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.connect())
+            .await
+    }
+
+    pub async fn disconnect(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.disconnect())
+            .await
+    }
+
+    /// Ask the agent for its identities and snapshot them into owned values
+    /// so they can be inspected and iterated across `.await` points.
+    pub async fn list_identities(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.list_identities())
+            .await?;
+
+        self.identities = self
+            .inner
+            .identities()
+            .map_err(Into::<io::Error>::into)?
+            .iter()
+            .map(|identity| AgentIdentity {
+                blob: identity.blob().to_vec(),
+                comment: identity.comment().to_owned(),
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    /// The identities snapshotted by the last call to [`list_identities`](Self::list_identities).
+    pub fn identities(&self) -> &[AgentIdentity] {
+        &self.identities
+    }
+
+    /// Authenticate `username` with the given identity, looking the raw
+    /// agent identity back up by matching its public key blob.
+    pub async fn userauth(&self, username: &str, identity: &AgentIdentity) -> io::Result<()> {
+        let inner = &self.inner;
+        let blob = identity.blob();
+
+        let found = self
+            .stream
+            .read_and_write_with(&self.session, || {
+                let raw_identities = inner.identities()?;
+                match find_identity_index(raw_identities.iter().map(|raw| raw.blob()), blob) {
+                    Some(index) => {
+                        inner.userauth(username, &raw_identities[index])?;
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                }
+            })
+            .await?;
+
+        if found {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "agent identity is no longer present",
+            ))
+        }
+    }
+}
+
+/// The blob-matching logic behind [`AsyncAgent::userauth`], pulled out as a
+/// pure function over plain blobs so it's unit-testable without a live
+/// ssh-agent (the rest of `userauth` only exists to get from/to real
+/// `ssh2::Identity` values, which can't be constructed without one).
+fn find_identity_index<'a>(
+    blobs: impl Iterator<Item = &'a [u8]>,
+    target: &[u8],
+) -> Option<usize> {
+    blobs.position(|blob| blob == target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_matching_blob() {
+        let blobs: Vec<&[u8]> = vec![b"one", b"two", b"three"];
+        assert_eq!(find_identity_index(blobs.into_iter(), b"two"), Some(1));
+    }
+
+    #[test]
+    fn returns_none_when_no_blob_matches() {
+        let blobs: Vec<&[u8]> = vec![b"one", b"two"];
+        assert_eq!(find_identity_index(blobs.into_iter(), b"absent"), None);
+    }
+
+    #[test]
+    fn returns_none_for_an_empty_identity_list() {
+        let blobs: Vec<&[u8]> = vec![];
+        assert_eq!(find_identity_index(blobs.into_iter(), b"anything"), None);
+    }
+}
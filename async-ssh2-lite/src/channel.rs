@@ -0,0 +1,269 @@
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use ssh2::{Channel, Session};
+
+use crate::session_stream::AsyncSessionStream;
+
+pub struct AsyncChannel<S> {
+    inner: Channel,
+    session: Session,
+    stream: Arc<S>,
+}
+
+impl<S> AsyncChannel<S> {
+    pub(crate) fn from_parts(inner: Channel, session: Session, stream: Arc<S>) -> Self {
+        Self {
+            inner,
+            session,
+            stream,
+        }
+    }
+}
+
+impl<S> AsyncChannel<S>
+where
+    S: AsyncSessionStream + Send + Sync,
+{
+    pub async fn setenv(&self, var: &str, val: &str) -> io::Result<()> {
+        let inner = &self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.setenv(var, val))
+            .await
+    }
+
+    pub async fn exec(&mut self, command: &str) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.exec(command))
+            .await
+    }
+
+    pub async fn shell(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.shell())
+            .await
+    }
+
+    pub async fn send_eof(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.send_eof())
+            .await
+    }
+
+    pub async fn wait_eof(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.wait_eof())
+            .await
+    }
+
+    pub async fn close(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.close())
+            .await
+    }
+
+    pub async fn wait_close(&mut self) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.wait_close())
+            .await
+    }
+
+    pub fn exit_status(&self) -> io::Result<i32> {
+        self.inner.exit_status().map_err(Into::into)
+    }
+
+    pub fn eof(&self) -> bool {
+        self.inner.eof()
+    }
+}
+
+//
+// extension
+//
+impl<S> AsyncChannel<S>
+where
+    S: AsyncSessionStream + Send + Sync,
+{
+    /// Request a pseudo-terminal advertising `$TERM` as `term`.
+    ///
+    /// This only sends the pty-req packet's terminal type and dimensions;
+    /// it does not push a terminfo entry to the server. A server that
+    /// doesn't already have an entry for `term` needs one installed
+    /// out-of-band (e.g. `infocmp | ssh host tic -`) before a full-screen
+    /// program will work correctly.
+    ///
+    /// FIXME: the original request for this method asked for an optional
+    /// way to push raw terminfo bytes for servers missing the client's
+    /// terminal definition. The pty-req "encoded terminal modes" field is
+    /// the wrong place for that (it's a fixed termios encoding, not a
+    /// terminfo entry), and there's no other field in `ssh2::Channel`'s
+    /// pty-req that carries it, so that capability is still missing here.
+    /// Needs a real design (e.g. exec'ing `tic` over a side channel) before
+    /// it can be added back; tracked as an open follow-up, not dropped.
+    pub async fn request_pty(
+        &mut self,
+        term: &str,
+        dimensions: Option<(u32, u32, u32, u32)>,
+    ) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || inner.request_pty(term, None, dimensions))
+            .await
+    }
+
+    /// Resize an already-requested pty. Safe to call at any time after
+    /// `shell()`/`exec()`, e.g. in response to a `SIGWINCH`.
+    pub async fn request_pty_size(
+        &mut self,
+        width: u32,
+        height: u32,
+        width_px: Option<u32>,
+        height_px: Option<u32>,
+    ) -> io::Result<()> {
+        let inner = &mut self.inner;
+
+        self.stream
+            .read_and_write_with(&self.session, || {
+                inner.request_pty_size(width, height, width_px, height_px)
+            })
+            .await
+    }
+
+    /// Request a shell on an already-pty'd channel, write `stdin` to it,
+    /// read its stdout to EOF, then drive it to completion (EOF + close),
+    /// returning the remote exit status alongside the captured output.
+    /// Only the stdout stream is read; stderr is not captured.
+    ///
+    /// `stdin` is written in full and then the channel's write side is
+    /// closed (`send_eof`), matching `ssh host </dev/full_input` semantics —
+    /// pass `&[]` for a shell that doesn't read stdin. Output is read only
+    /// after that, and then to EOF rather than left unread: libssh2 channels are flow
+    /// controlled by a receive window that only advances as the local side
+    /// reads, so anything producing more output than the window holds would
+    /// otherwise stall forever.
+    pub async fn run_shell_to_completion(&mut self, stdin: &[u8]) -> io::Result<(i32, Vec<u8>)> {
+        self.shell().await?;
+        AsyncWriteExt::write_all(self, stdin).await?;
+        self.send_eof().await?;
+
+        let mut output = Vec::new();
+        AsyncReadExt::read_to_end(self, &mut output).await?;
+
+        self.wait_eof().await?;
+        self.close().await?;
+        self.wait_close().await?;
+        Ok((self.exit_status()?, output))
+    }
+
+    /// Run `command`, write `stdin` to it, read its stdout to EOF, then
+    /// drive the channel to completion (EOF + close), returning the remote
+    /// exit status alongside the captured output. Only the stdout stream is
+    /// read; stderr is not captured.
+    ///
+    /// See [`Self::run_shell_to_completion`] for why `stdin` is written and
+    /// EOF'd up front and why output is drained rather than left unread.
+    pub async fn run_exec_to_completion(
+        &mut self,
+        command: &str,
+        stdin: &[u8],
+    ) -> io::Result<(i32, Vec<u8>)> {
+        self.exec(command).await?;
+        AsyncWriteExt::write_all(self, stdin).await?;
+        self.send_eof().await?;
+
+        let mut output = Vec::new();
+        AsyncReadExt::read_to_end(self, &mut output).await?;
+
+        self.wait_eof().await?;
+        self.close().await?;
+        self.wait_close().await?;
+        Ok((self.exit_status()?, output))
+    }
+}
+
+impl<S> AsyncRead for AsyncChannel<S>
+where
+    S: AsyncSessionStream + Send + Sync + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        match io::Read::read(&mut this.inner, buf) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+impl<S> AsyncWrite for AsyncChannel<S>
+where
+    S: AsyncSessionStream + Send + Sync + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        match io::Write::write(&mut this.inner, buf) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match io::Write::flush(&mut this.inner) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.inner.send_eof().map_err(io::Error::from) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! TCP port forwarding (`-L`/`-R` style) built on top of the low-level
+//! `channel_direct_tcpip`/`channel_forward_listen` primitives on [`AsyncSession`].
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::io::{copy, AsyncReadExt};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt as _;
+
+#[cfg(feature = "tokio")]
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(feature = "tokio")]
+use tokio_util::compat::TokioAsyncReadCompatExt as _;
+
+#[cfg(feature = "async-io")]
+use async_io::Async;
+#[cfg(feature = "async-io")]
+use futures_util::FutureExt as _;
+#[cfg(feature = "async-io")]
+use std::net::{TcpListener as StdTcpListener, TcpStream as StdTcpStream};
+
+use crate::{session::AsyncSession, session_stream::AsyncSessionStream};
+
+/// How long to back off after an `accept()` error before trying again, so a
+/// persistent error (e.g. `EMFILE`) can't spin the accept loop at 100% CPU.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(200);
+
+/// A running port forward.
+///
+/// Dropping this handle stops the background task(s) that are pumping bytes
+/// for this forward, along with every connection it has spawned so far.
+pub struct ForwardHandle {
+    #[cfg(feature = "tokio")]
+    task: tokio::task::JoinHandle<()>,
+    #[cfg(all(feature = "async-io", not(feature = "tokio")))]
+    task: async_global_executor::Task<()>,
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for ForwardHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl<S> AsyncSession<S>
+where
+    S: AsyncSessionStream + Send + Sync + 'static,
+{
+    /// Open a local forward: bind `local_addr`, and for every accepted
+    /// connection open a `direct-tcpip` channel to `(remote_host, remote_port)`
+    /// and pump bytes between the two until both directions reach EOF.
+    pub async fn forward_local(
+        &self,
+        local_addr: SocketAddr,
+        remote_host: &str,
+        remote_port: u16,
+    ) -> io::Result<ForwardHandle> {
+        let remote_host = remote_host.to_owned();
+
+        #[cfg(feature = "tokio")]
+        {
+            let listener = TcpListener::bind(local_addr).await?;
+            let inner = self.clone_for_forward();
+
+            let task = tokio::spawn(async move {
+                let mut connections = FuturesUnordered::new();
+
+                loop {
+                    tokio::select! {
+                        accepted = listener.accept() => {
+                            let (socket, peer) = match accepted {
+                                Ok(accepted) => accepted,
+                                Err(_) => {
+                                    backoff_sleep().await;
+                                    continue;
+                                }
+                            };
+                            let inner = inner.clone();
+                            let remote_host = remote_host.clone();
+                            connections.push(async move {
+                                let _ = forward_one(&inner, socket, peer, &remote_host, remote_port).await;
+                            });
+                        }
+                        Some(()) = connections.next(), if !connections.is_empty() => {}
+                    }
+                }
+            });
+
+            return Ok(ForwardHandle { task });
+        }
+
+        #[cfg(all(feature = "async-io", not(feature = "tokio")))]
+        {
+            let listener = Async::<StdTcpListener>::bind(local_addr)?;
+            let inner = self.clone_for_forward();
+
+            let task = async_global_executor::spawn(async move {
+                let mut connections = FuturesUnordered::new();
+
+                loop {
+                    futures_util::select! {
+                        accepted = listener.accept().fuse() => {
+                            let (socket, peer) = match accepted {
+                                Ok(accepted) => accepted,
+                                Err(_) => {
+                                    backoff_sleep().await;
+                                    continue;
+                                }
+                            };
+                            let inner = inner.clone();
+                            let remote_host = remote_host.clone();
+                            connections.push(async move {
+                                let _ = forward_one(&inner, socket, peer, &remote_host, remote_port).await;
+                            });
+                        }
+                        _ = connections.next() => {}
+                    }
+                }
+            });
+
+            return Ok(ForwardHandle { task });
+        }
+
+        #[cfg(not(any(feature = "tokio", feature = "async-io")))]
+        {
+            let _ = (local_addr, remote_host, remote_port);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "forward_local requires the \"tokio\" or \"async-io\" feature",
+            ))
+        }
+    }
+
+    /// Open a remote forward: ask the server to listen on `remote_port` on
+    /// all of its addresses, and for every inbound channel it hands back,
+    /// connect out to `(local_host, local_port)` and pump bytes both ways.
+    ///
+    /// Returns the handle alongside the port the server actually bound,
+    /// which is the only way to learn the chosen port when `remote_port` is
+    /// `0` (let the server pick one).
+    pub async fn forward_remote(
+        &self,
+        remote_port: u16,
+        local_host: &str,
+        local_port: u16,
+    ) -> io::Result<(ForwardHandle, u16)> {
+        let (mut listener, bound_port) = self
+            .channel_forward_listen(remote_port, None, None)
+            .await?;
+
+        let local_host = local_host.to_owned();
+
+        #[cfg(feature = "tokio")]
+        {
+            let task = tokio::spawn(async move {
+                let mut connections = FuturesUnordered::new();
+
+                loop {
+                    tokio::select! {
+                        accepted = listener.accept() => {
+                            let channel = match accepted {
+                                Ok(channel) => channel,
+                                Err(_) => {
+                                    backoff_sleep().await;
+                                    continue;
+                                }
+                            };
+                            let local_host = local_host.clone();
+                            connections.push(async move {
+                                let socket = match TcpStream::connect((local_host.as_str(), local_port)).await {
+                                    Ok(socket) => socket,
+                                    Err(_) => return,
+                                };
+                                let _ = pump(channel, socket.compat()).await;
+                            });
+                        }
+                        Some(()) = connections.next(), if !connections.is_empty() => {}
+                    }
+                }
+            });
+
+            return Ok((ForwardHandle { task }, bound_port));
+        }
+
+        #[cfg(all(feature = "async-io", not(feature = "tokio")))]
+        {
+            let task = async_global_executor::spawn(async move {
+                let mut connections = FuturesUnordered::new();
+
+                loop {
+                    futures_util::select! {
+                        accepted = listener.accept().fuse() => {
+                            let channel = match accepted {
+                                Ok(channel) => channel,
+                                Err(_) => {
+                                    backoff_sleep().await;
+                                    continue;
+                                }
+                            };
+                            let local_host = local_host.clone();
+                            connections.push(async move {
+                                let socket = match Async::<StdTcpStream>::connect((local_host.as_str(), local_port)).await {
+                                    Ok(socket) => socket,
+                                    Err(_) => return,
+                                };
+                                let _ = pump(channel, socket).await;
+                            });
+                        }
+                        _ = connections.next() => {}
+                    }
+                }
+            });
+
+            return Ok((ForwardHandle { task }, bound_port));
+        }
+
+        #[cfg(not(any(feature = "tokio", feature = "async-io")))]
+        {
+            let _ = (listener, local_host, local_port, bound_port);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "forward_remote requires the \"tokio\" or \"async-io\" feature",
+            ))
+        }
+    }
+
+    /// A cheap clone suitable for moving into a spawned forwarding task: the
+    /// underlying libssh2 session handle and transport are already `Arc`-backed.
+    fn clone_for_forward(&self) -> AsyncSession<S> {
+        self.clone()
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn backoff_sleep() {
+    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+}
+
+#[cfg(all(feature = "async-io", not(feature = "tokio")))]
+async fn backoff_sleep() {
+    async_io::Timer::after(ACCEPT_ERROR_BACKOFF).await;
+}
+
+#[cfg(feature = "tokio")]
+async fn forward_one<S>(
+    session: &AsyncSession<S>,
+    socket: TcpStream,
+    peer: SocketAddr,
+    remote_host: &str,
+    remote_port: u16,
+) -> io::Result<()>
+where
+    S: AsyncSessionStream + Send + Sync + 'static,
+{
+    let peer_ip = peer.ip().to_string();
+    let channel = session
+        .channel_direct_tcpip(remote_host, remote_port, Some((peer_ip.as_str(), peer.port())))
+        .await?;
+    pump(channel, socket.compat()).await
+}
+
+#[cfg(all(feature = "async-io", not(feature = "tokio")))]
+async fn forward_one<S>(
+    session: &AsyncSession<S>,
+    socket: Async<StdTcpStream>,
+    peer: SocketAddr,
+    remote_host: &str,
+    remote_port: u16,
+) -> io::Result<()>
+where
+    S: AsyncSessionStream + Send + Sync + 'static,
+{
+    let peer_ip = peer.ip().to_string();
+    let channel = session
+        .channel_direct_tcpip(remote_host, remote_port, Some((peer_ip.as_str(), peer.port())))
+        .await?;
+    pump(channel, socket).await
+}
+
+/// Copy bytes in both directions between `a` and `b` until each side has
+/// seen EOF, closing the corresponding destination's write half as soon as
+/// its direction goes dry so a half-close on one side is propagated to the
+/// other instead of leaving the peer waiting for data that's never coming.
+/// When a channel direction would block, `AsyncChannel`'s `poll_read`/
+/// `poll_write` wake their task at once instead of waiting for
+/// `block_directions()` readiness, so a stalled channel is re-polled
+/// continuously.
+async fn pump<A, B>(a: A, b: B) -> io::Result<()>
+where
+    A: futures_util::io::AsyncRead + futures_util::io::AsyncWrite + Unpin,
+    B: futures_util::io::AsyncRead + futures_util::io::AsyncWrite + Unpin,
+{
+    use futures_util::io::AsyncWriteExt as _;
+
+    let (a_read, a_write) = a.split();
+    let (b_read, b_write) = b.split();
+
+    let a_to_b = async move {
+        let mut b_write = b_write;
+        let result = copy(a_read, &mut b_write).await;
+        let _ = b_write.close().await;
+        result
+    };
+    let b_to_a = async move {
+        let mut a_write = a_write;
+        let result = copy(b_read, &mut a_write).await;
+        let _ = a_write.close().await;
+        result
+    };
+
+    futures_util::future::try_join(a_to_b, b_to_a).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    use futures_util::io::{AsyncRead, AsyncWrite, Cursor};
+
+    use super::*;
+
+    /// One side of an in-memory duplex pipe: reads from a fixed buffer,
+    /// writes into a buffer shared with whoever wants to inspect the result.
+    struct MockDuplex {
+        read: Cursor<Vec<u8>>,
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl AsyncRead for MockDuplex {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.get_mut().read).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for MockDuplex {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn pump_copies_both_directions_and_stops_on_eof() {
+        let a_written = Arc::new(Mutex::new(Vec::new()));
+        let b_written = Arc::new(Mutex::new(Vec::new()));
+
+        let a = MockDuplex {
+            read: Cursor::new(b"hello from a".to_vec()),
+            written: a_written.clone(),
+        };
+        let b = MockDuplex {
+            read: Cursor::new(b"hello from b".to_vec()),
+            written: b_written.clone(),
+        };
+
+        futures_lite::future::block_on(pump(a, b)).unwrap();
+
+        assert_eq!(&*a_written.lock().unwrap(), b"hello from b");
+        assert_eq!(&*b_written.lock().unwrap(), b"hello from a");
+    }
+}
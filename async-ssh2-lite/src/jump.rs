@@ -0,0 +1,172 @@
+//! Chained / jump-host sessions: run an `AsyncSession` over an already
+//! established `AsyncChannel` instead of a raw socket (OpenSSH's `ProxyJump`).
+//!
+//! `ssh2::Session::set_tcp_stream` only accepts a raw OS socket, so a second
+//! (inner) session can't read/write a logical `direct-tcpip` channel
+//! directly. Instead we open a connected pair of local unix-domain sockets,
+//! hand one end's raw fd to the inner session exactly like a normal
+//! connection, and spawn a task that pumps bytes between the other end and
+//! the bastion's `AsyncChannel`. From the inner session's point of view this
+//! is indistinguishable from a direct TCP connection, so `handshake`,
+//! `userauth_*`, `sftp`, and `channel_session` all work unchanged, and jump
+//! chains nest to arbitrary depth by repeating this over the inner session's
+//! own channels.
+//!
+//! Unix-only: it relies on `UnixStream::pair()` to synthesize something with
+//! a raw fd for the inner session to bind to.
+#![cfg(unix)]
+
+use std::io;
+
+use futures_util::io::{copy, AsyncReadExt as _};
+
+use crate::{
+    channel::AsyncChannel,
+    session::{AsyncSession, SessionConfiguration},
+    session_stream::AsyncSessionStream,
+};
+
+#[cfg(feature = "tokio")]
+use tokio_util::compat::TokioAsyncReadCompatExt as _;
+
+/// The local half of the socketpair an inner, jumped-through session is
+/// actually backed by.
+#[cfg(feature = "tokio")]
+pub type JumpStream = tokio::net::UnixStream;
+
+#[cfg(all(feature = "async-io", not(feature = "tokio")))]
+pub type JumpStream = async_io::Async<std::os::unix::net::UnixStream>;
+
+/// Establish a new `AsyncSession` whose transport is `channel` — typically a
+/// `direct-tcpip` channel opened on a bastion session toward the real
+/// target, e.g. `bastion.channel_direct_tcpip(target_host, 22, None).await?`.
+pub async fn connect_through<S>(
+    channel: AsyncChannel<S>,
+    configuration: Option<SessionConfiguration>,
+) -> io::Result<AsyncSession<JumpStream>>
+where
+    S: AsyncSessionStream + Send + Sync + 'static,
+{
+    #[cfg(feature = "tokio")]
+    {
+        let (local, remote) = tokio::net::UnixStream::pair()?;
+        // `pump` needs the `futures_util::io::AsyncRead`/`AsyncWrite` traits;
+        // only `remote` (the half we drive ourselves) needs the compat shim —
+        // `local` is handed to `AsyncSession::new` as a plain raw-fd socket,
+        // same as every other transport in this crate.
+        tokio::spawn(pump(channel, remote.compat()));
+
+        let mut session = AsyncSession::new(local, configuration)?;
+        session.handshake().await?;
+        return Ok(session);
+    }
+
+    #[cfg(all(feature = "async-io", not(feature = "tokio")))]
+    {
+        let (local, remote) = std::os::unix::net::UnixStream::pair()?;
+        let local = async_io::Async::new(local)?;
+        let remote = async_io::Async::new(remote)?;
+        async_global_executor::spawn(pump(channel, remote)).detach();
+
+        let mut session = AsyncSession::new(local, configuration)?;
+        session.handshake().await?;
+        return Ok(session);
+    }
+
+    #[cfg(not(any(feature = "tokio", feature = "async-io")))]
+    {
+        let _ = (channel, configuration);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "connect_through requires the \"tokio\" or \"async-io\" feature",
+        ))
+    }
+}
+
+/// Copy bytes in both directions between the bastion channel and the local
+/// socketpair end that the inner session is reading/writing.
+///
+/// Generic over plain `AsyncRead + AsyncWrite` rather than hardcoded to
+/// `AsyncChannel<S>` so the copy loop can be unit-tested against an
+/// in-memory mock instead of a live channel.
+async fn pump<C, L>(channel: C, local: L)
+where
+    C: futures_util::io::AsyncRead + futures_util::io::AsyncWrite + Unpin,
+    L: futures_util::io::AsyncRead + futures_util::io::AsyncWrite + Unpin,
+{
+    let (channel_read, channel_write) = channel.split();
+    let (local_read, local_write) = local.split();
+
+    let _ = futures_util::future::try_join(
+        copy(channel_read, local_write),
+        copy(local_read, channel_write),
+    )
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    use futures_util::io::{AsyncRead, AsyncWrite, Cursor};
+
+    use super::*;
+
+    /// One side of an in-memory duplex pipe: reads from a fixed buffer,
+    /// writes into a buffer shared with whoever wants to inspect the result.
+    struct MockDuplex {
+        read: Cursor<Vec<u8>>,
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl AsyncRead for MockDuplex {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.get_mut().read).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for MockDuplex {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn pump_copies_both_directions_and_stops_on_eof() {
+        let channel_written = Arc::new(Mutex::new(Vec::new()));
+        let local_written = Arc::new(Mutex::new(Vec::new()));
+
+        let channel = MockDuplex {
+            read: Cursor::new(b"from the bastion channel".to_vec()),
+            written: channel_written.clone(),
+        };
+        let local = MockDuplex {
+            read: Cursor::new(b"from the inner session".to_vec()),
+            written: local_written.clone(),
+        };
+
+        futures_lite::future::block_on(pump(channel, local));
+
+        assert_eq!(&*channel_written.lock().unwrap(), b"from the inner session");
+        assert_eq!(&*local_written.lock().unwrap(), b"from the bastion channel");
+    }
+}
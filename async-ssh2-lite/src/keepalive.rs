@@ -0,0 +1,137 @@
+//! A background driver for libssh2's (passive) keepalive support.
+//!
+//! `SessionConfiguration::set_keepalive` only tells libssh2 what interval to
+//! use; libssh2 never sends a keepalive packet on its own, someone has to
+//! call `keepalive_send()` on a timer. [`AsyncSession::spawn_keepalive`]
+//! spawns that timer for long-lived (e.g. forwarded/tunnelled) sessions that
+//! would otherwise be silently dropped by an intermediate NAT/firewall.
+
+use std::io;
+use std::time::Duration;
+
+use futures_util::StreamExt as _;
+
+use crate::{session::AsyncSession, session_stream::AsyncSessionStream};
+
+/// Owns the spawned keepalive task; dropping it stops sending keepalives.
+pub struct KeepaliveHandle {
+    #[cfg(feature = "tokio")]
+    task: tokio::task::JoinHandle<()>,
+    #[cfg(all(feature = "async-io", not(feature = "tokio")))]
+    task: async_global_executor::Task<()>,
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for KeepaliveHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The receiving half of the keepalive driver's error channel. Yields the
+/// error from the first failed `keepalive_send()`, after which the driver
+/// stops and `recv()` returns `None`.
+pub struct KeepaliveErrors(futures_channel::mpsc::UnboundedReceiver<io::Error>);
+
+impl KeepaliveErrors {
+    pub async fn recv(&mut self) -> Option<io::Error> {
+        self.0.next().await
+    }
+}
+
+impl<S> AsyncSession<S>
+where
+    S: AsyncSessionStream + Send + Sync + 'static,
+{
+    /// Spawn a background task that calls `keepalive_send()` every `interval`.
+    /// `interval` is used as given; the interval passed to
+    /// `SessionConfiguration::set_keepalive` is not consulted.
+    ///
+    /// Returns a handle that stops the driver on drop, and an error stream
+    /// that yields the transport error if a keepalive ever fails.
+    pub fn spawn_keepalive(
+        &self,
+        interval: Duration,
+    ) -> io::Result<(KeepaliveHandle, KeepaliveErrors)> {
+        validate_keepalive_interval(interval)?;
+
+        let (tx, rx) = futures_channel::mpsc::unbounded();
+        let session = self.clone();
+
+        #[cfg(feature = "tokio")]
+        {
+            let task = tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.tick().await;
+
+                loop {
+                    ticker.tick().await;
+
+                    if let Err(err) = session.keepalive_send().await {
+                        let _ = tx.unbounded_send(err.into());
+                        break;
+                    }
+                }
+            });
+
+            return Ok((KeepaliveHandle { task }, KeepaliveErrors(rx)));
+        }
+
+        #[cfg(all(feature = "async-io", not(feature = "tokio")))]
+        {
+            let task = async_global_executor::spawn(async move {
+                loop {
+                    async_io::Timer::after(interval).await;
+
+                    if let Err(err) = session.keepalive_send().await {
+                        let _ = tx.unbounded_send(err.into());
+                        break;
+                    }
+                }
+            });
+
+            return Ok((KeepaliveHandle { task }, KeepaliveErrors(rx)));
+        }
+
+        #[cfg(not(any(feature = "tokio", feature = "async-io")))]
+        {
+            let _ = interval;
+            drop(tx);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "spawn_keepalive requires the \"tokio\" or \"async-io\" feature",
+            ))
+        }
+    }
+}
+
+/// `tokio::time::interval()` panics on `Duration::ZERO`, so this is checked
+/// up front rather than letting the tokio branch of `spawn_keepalive` abort
+/// the process — pulled out as its own function so it's unit-testable
+/// without constructing a session.
+fn validate_keepalive_interval(interval: Duration) -> io::Result<()> {
+    if interval.is_zero() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "spawn_keepalive interval must not be zero",
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_interval_is_rejected() {
+        let err = validate_keepalive_interval(Duration::ZERO).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn nonzero_interval_is_accepted() {
+        validate_keepalive_interval(Duration::from_secs(30)).unwrap();
+    }
+}
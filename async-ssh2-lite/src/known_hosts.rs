@@ -0,0 +1,287 @@
+//! Host-key verification and trust-on-first-use (TOFU) support on top of
+//! `AsyncSession::known_hosts()`.
+//!
+//! `ssh2::KnownHosts`'s entries borrow from the `KnownHosts` handle itself,
+//! so they can't be held across an `.await`. Whenever we need to act on more
+//! than one entry (e.g. to report what's already on file for a mismatched
+//! host), we copy the bits we need into an owned [`KnownHostEntry`] first and
+//! only then perform the async known_hosts file I/O — mirroring the
+//! owned-copy approach the `ssh2` crate itself took when it made its agent
+//! API `Send`-safe.
+
+use std::io;
+use std::path::Path;
+
+use ssh2::{CheckResult, HostKeyType, KnownHostFileKind, KnownHosts};
+
+use crate::session::AsyncSession;
+
+/// How to react when a host key is missing from the known_hosts file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyPolicy {
+    /// Trust-on-first-use: silently add unknown hosts, but never paper over
+    /// a key that changed.
+    Tofu,
+    /// Refuse to proceed unless the host is already known with a matching key.
+    Strict,
+}
+
+/// An owned copy of one `known_hosts` entry, safe to hold across an `.await`.
+#[derive(Debug, Clone)]
+pub struct KnownHostEntry {
+    pub name: String,
+    pub key_base64: String,
+}
+
+/// The outcome of [`AsyncSession::check_host_key`].
+#[derive(Debug, Clone)]
+pub enum HostKeyDecision {
+    /// The server's key matches the entry already on file.
+    Match,
+    /// The host was already known, but under a different key. Contains the
+    /// entries on file for this host, for diagnostics. `check_host_key`
+    /// does not return this variant: under either policy a mismatched key
+    /// is reported as an `InvalidData` error instead.
+    Mismatch(Vec<KnownHostEntry>),
+    /// The host had no entry; under [`HostKeyPolicy::Tofu`] it has now been
+    /// added and written back to `known_hosts_path`.
+    NotFound,
+}
+
+impl<S> AsyncSession<S>
+where
+    S: crate::session_stream::AsyncSessionStream + Send + Sync,
+{
+    /// Verify the server's host key against `known_hosts_path`, applying
+    /// `policy` when the host is unknown or the key doesn't match.
+    ///
+    /// Must be called after `handshake()`, since it reads the key the server
+    /// presented during the handshake via `host_key()`.
+    pub async fn check_host_key(
+        &self,
+        host: &str,
+        port: u16,
+        known_hosts_path: &Path,
+        policy: HostKeyPolicy,
+    ) -> io::Result<HostKeyDecision> {
+        let (key, key_type) = self.host_key().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotConnected,
+                "no host key available; call check_host_key() after handshake()",
+            )
+        })?;
+        let key = key.to_vec();
+
+        let contents = match read_to_string(known_hosts_path).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut known_hosts = self.known_hosts()?;
+        if !contents.is_empty() {
+            known_hosts
+                .read_str(&contents, KnownHostFileKind::OpenSSH)
+                .map_err(io::Error::from)?;
+        }
+
+        let (decision, serialized) =
+            evaluate_host_key(&mut known_hosts, host, port, &key, key_type, policy)?;
+        drop(known_hosts);
+
+        if let Some(serialized) = serialized {
+            write_string(known_hosts_path, &serialized).await?;
+        }
+
+        Ok(decision)
+    }
+}
+
+/// The synchronous decision logic behind [`AsyncSession::check_host_key`],
+/// pulled out so it can be unit-tested without any file or network I/O: it
+/// only touches the in-memory `known_hosts` collection that's already been
+/// populated by the caller.
+///
+/// Returns the decision, plus the known_hosts file contents to persist when
+/// [`HostKeyPolicy::Tofu`] added a new entry.
+fn evaluate_host_key(
+    known_hosts: &mut KnownHosts,
+    host: &str,
+    port: u16,
+    key: &[u8],
+    key_type: HostKeyType,
+    policy: HostKeyPolicy,
+) -> io::Result<(HostKeyDecision, Option<String>)> {
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok((HostKeyDecision::Match, None)),
+        CheckResult::Mismatch => {
+            let entries = known_hosts
+                .hosts()
+                .map_err(io::Error::from)?
+                .iter()
+                .filter(|entry| entry.name() == Some(host))
+                .map(|entry| KnownHostEntry {
+                    name: entry.name().unwrap_or_default().to_owned(),
+                    key_base64: entry.key().to_owned(),
+                })
+                .collect::<Vec<_>>();
+
+            match policy {
+                HostKeyPolicy::Strict => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("host key for {host} does not match known_hosts"),
+                )),
+                // TOFU trusts new hosts; it must never silently accept a
+                // key that changed, so a mismatch is always an error.
+                HostKeyPolicy::Tofu => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "host key for {host} does not match the {} entry/entries on file",
+                        entries.len()
+                    ),
+                )),
+            }
+        }
+        CheckResult::NotFound => match policy {
+            HostKeyPolicy::Strict => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("host key for {host} not found in known_hosts"),
+            )),
+            HostKeyPolicy::Tofu => {
+                known_hosts
+                    .add(host, key, "added by async-ssh2-lite (TOFU)", key_type.into())
+                    .map_err(io::Error::from)?;
+                let serialized = known_hosts
+                    .write_string(KnownHostFileKind::OpenSSH)
+                    .map_err(io::Error::from)?;
+
+                Ok((HostKeyDecision::NotFound, Some(serialized)))
+            }
+        },
+        CheckResult::Failure => Err(io::Error::new(
+            io::ErrorKind::Other,
+            "failed to check host key against known_hosts",
+        )),
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn read_to_string(path: &Path) -> io::Result<String> {
+    tokio::fs::read_to_string(path).await
+}
+
+#[cfg(feature = "tokio")]
+async fn write_string(path: &Path, contents: &str) -> io::Result<()> {
+    tokio::fs::write(path, contents).await
+}
+
+#[cfg(all(feature = "async-io", not(feature = "tokio")))]
+async fn read_to_string(path: &Path) -> io::Result<String> {
+    async_fs::read_to_string(path).await
+}
+
+#[cfg(all(feature = "async-io", not(feature = "tokio")))]
+async fn write_string(path: &Path, contents: &str) -> io::Result<()> {
+    async_fs::write(path, contents).await
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-io")))]
+async fn read_to_string(path: &Path) -> io::Result<String> {
+    std::fs::read_to_string(path)
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-io")))]
+async fn write_string(path: &Path, contents: &str) -> io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use ssh2::Session;
+
+    use super::*;
+
+    const HOST: &str = "example.com";
+    const PORT: u16 = 22;
+    const KEY: &[u8] = b"this-is-a-fake-host-key-for-testing-only";
+    const OTHER_KEY: &[u8] = b"this-is-a-different-fake-host-key";
+
+    fn fresh_known_hosts() -> KnownHosts {
+        Session::new().unwrap().known_hosts().unwrap()
+    }
+
+    #[test]
+    fn not_found_under_strict_is_rejected() {
+        let mut known_hosts = fresh_known_hosts();
+
+        let err = evaluate_host_key(
+            &mut known_hosts,
+            HOST,
+            PORT,
+            KEY,
+            HostKeyType::Rsa,
+            HostKeyPolicy::Strict,
+        )
+        .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn not_found_under_tofu_adds_and_returns_contents_to_persist() {
+        let mut known_hosts = fresh_known_hosts();
+
+        let (decision, serialized) = evaluate_host_key(
+            &mut known_hosts,
+            HOST,
+            PORT,
+            KEY,
+            HostKeyType::Rsa,
+            HostKeyPolicy::Tofu,
+        )
+        .unwrap();
+
+        assert!(matches!(decision, HostKeyDecision::NotFound));
+        let serialized = serialized.expect("tofu add must return contents to persist");
+        assert!(serialized.contains(HOST));
+    }
+
+    #[test]
+    fn match_is_accepted_under_either_policy() {
+        for policy in [HostKeyPolicy::Strict, HostKeyPolicy::Tofu] {
+            let mut known_hosts = fresh_known_hosts();
+            known_hosts
+                .add(HOST, KEY, "test fixture", HostKeyType::Rsa.into())
+                .unwrap();
+
+            let (decision, serialized) =
+                evaluate_host_key(&mut known_hosts, HOST, PORT, KEY, HostKeyType::Rsa, policy)
+                    .unwrap();
+
+            assert!(matches!(decision, HostKeyDecision::Match));
+            assert!(serialized.is_none());
+        }
+    }
+
+    #[test]
+    fn mismatch_is_rejected_under_either_policy() {
+        for policy in [HostKeyPolicy::Strict, HostKeyPolicy::Tofu] {
+            let mut known_hosts = fresh_known_hosts();
+            known_hosts
+                .add(HOST, KEY, "test fixture", HostKeyType::Rsa.into())
+                .unwrap();
+
+            let err = evaluate_host_key(
+                &mut known_hosts,
+                HOST,
+                PORT,
+                OTHER_KEY,
+                HostKeyType::Rsa,
+                policy,
+            )
+            .unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
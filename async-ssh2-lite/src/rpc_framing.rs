@@ -0,0 +1,269 @@
+//! `Content-Length`-framed message transport over an [`AsyncChannel`],
+//! for driving remote LSP/JSON-RPC servers spawned over SSH (as tools like
+//! `distant` do): a process is exec'd on the channel and its stdio is framed
+//! as `Content-Length: N\r\n\r\n<N bytes of body>`.
+
+use std::io;
+
+use futures_util::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+const HEADER_TERMINATOR: &[u8] = b"\r\n\r\n";
+
+/// Reads and writes `Content-Length`-framed messages over an
+/// `AsyncRead + AsyncWrite` channel.
+///
+/// Construct it from a channel that has already had a remote process
+/// `exec`'d on it (e.g. a language server, via [`crate::channel::AsyncChannel`]),
+/// then use [`Self::read_message`] and [`Self::write_message`] to exchange
+/// whole, de-framed message bodies.
+pub struct ContentLengthTransport<T> {
+    channel: T,
+    read_buf: Vec<u8>,
+}
+
+impl<T> ContentLengthTransport<T> {
+    pub fn new(channel: T) -> Self {
+        Self {
+            channel,
+            read_buf: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.channel
+    }
+}
+
+impl<T> ContentLengthTransport<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Read one complete framed message, buffering partial headers/bodies
+    /// across however many channel reads it takes.
+    ///
+    /// Returns `Ok(None)` on a clean EOF that lands exactly on a message
+    /// boundary (the remote process exited); any other EOF, or a header
+    /// that doesn't parse, is an error.
+    pub async fn read_message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let header_end = loop {
+            if let Some(pos) = find_subslice(&self.read_buf, HEADER_TERMINATOR) {
+                break pos + HEADER_TERMINATOR.len();
+            }
+
+            if !self.fill_buf().await? {
+                if self.read_buf.is_empty() {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "remote process closed its output mid-header",
+                ));
+            }
+        };
+
+        let content_length = parse_content_length(&self.read_buf[..header_end])?;
+
+        while self.read_buf.len() < header_end + content_length {
+            if !self.fill_buf().await? {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "remote process closed its output mid-body",
+                ));
+            }
+        }
+
+        let body = self.read_buf[header_end..header_end + content_length].to_vec();
+        self.read_buf.drain(..header_end + content_length);
+
+        Ok(Some(body))
+    }
+
+    /// Frame and write `body` as a single `Content-Length` message.
+    pub async fn write_message(&mut self, body: &[u8]) -> io::Result<()> {
+        let header = format!("Content-Length: {}\r\n\r\n", body.len());
+
+        self.channel.write_all(header.as_bytes()).await?;
+        self.channel.write_all(body).await?;
+        self.channel.flush().await
+    }
+
+    /// Read more bytes from the channel into `read_buf`. Returns `Ok(false)`
+    /// on EOF, `Ok(true)` if at least one byte was read.
+    async fn fill_buf(&mut self) -> io::Result<bool> {
+        let mut chunk = [0u8; 8192];
+        let n = self.channel.read(&mut chunk).await?;
+
+        if n == 0 {
+            return Ok(false);
+        }
+
+        self.read_buf.extend_from_slice(&chunk[..n]);
+
+        Ok(true)
+    }
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn parse_content_length(header: &[u8]) -> io::Result<usize> {
+    let header = std::str::from_utf8(header)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "message header is not utf8"))?;
+
+    header
+        .split("\r\n")
+        .find_map(|line| line.strip_prefix("Content-Length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message header is missing a valid Content-Length",
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_util::io::Cursor;
+
+    use super::*;
+
+    /// A fake channel that hands back `read` to readers one chunk at a time
+    /// (to exercise buffering across partial reads) and records everything
+    /// written to it.
+    struct MockChannel {
+        read: Cursor<Vec<u8>>,
+        chunk_size: usize,
+        written: Vec<u8>,
+    }
+
+    impl MockChannel {
+        fn new(data: &[u8], chunk_size: usize) -> Self {
+            Self {
+                read: Cursor::new(data.to_vec()),
+                chunk_size,
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncRead for MockChannel {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            let limit = this.chunk_size.min(buf.len());
+            Pin::new(&mut this.read).poll_read(cx, &mut buf[..limit])
+        }
+    }
+
+    impl AsyncWrite for MockChannel {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.get_mut().written.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn find_subslice_finds_and_misses() {
+        assert_eq!(find_subslice(b"abc\r\n\r\ndef", b"\r\n\r\n"), Some(3));
+        assert_eq!(find_subslice(b"no terminator here", b"\r\n\r\n"), None);
+        assert_eq!(find_subslice(b"", b"\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn parse_content_length_reads_the_header_value() {
+        assert_eq!(
+            parse_content_length(b"Content-Length: 42\r\n\r\n").unwrap(),
+            42
+        );
+        assert_eq!(
+            parse_content_length(b"X-Foo: bar\r\nContent-Length: 7\r\n\r\n").unwrap(),
+            7
+        );
+    }
+
+    #[test]
+    fn parse_content_length_rejects_missing_or_invalid_header() {
+        assert!(parse_content_length(b"X-Foo: bar\r\n\r\n").is_err());
+        assert!(parse_content_length(b"Content-Length: not-a-number\r\n\r\n").is_err());
+        assert!(parse_content_length(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn read_message_assembles_a_body_across_partial_reads() {
+        let body = b"{\"jsonrpc\":\"2.0\"}";
+        let framed = format!("Content-Length: {}\r\n\r\n", body.len());
+        let mut data = framed.into_bytes();
+        data.extend_from_slice(body);
+
+        // Force the header and body to arrive in small, arbitrarily-split chunks.
+        let channel = MockChannel::new(&data, 3);
+        let mut transport = ContentLengthTransport::new(channel);
+
+        let message = futures_lite::future::block_on(transport.read_message())
+            .unwrap()
+            .unwrap();
+        assert_eq!(message, body);
+    }
+
+    #[test]
+    fn read_message_returns_none_on_clean_eof_at_a_boundary() {
+        let channel = MockChannel::new(b"", 8192);
+        let mut transport = ContentLengthTransport::new(channel);
+
+        let message = futures_lite::future::block_on(transport.read_message()).unwrap();
+        assert!(message.is_none());
+    }
+
+    #[test]
+    fn read_message_errors_on_eof_mid_header() {
+        let channel = MockChannel::new(b"Content-Length: 5\r\n", 8192);
+        let mut transport = ContentLengthTransport::new(channel);
+
+        let err = futures_lite::future::block_on(transport.read_message()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_message_errors_on_eof_mid_body() {
+        let channel = MockChannel::new(b"Content-Length: 5\r\n\r\nab", 8192);
+        let mut transport = ContentLengthTransport::new(channel);
+
+        let err = futures_lite::future::block_on(transport.read_message()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn write_message_frames_the_body_with_its_length() {
+        let channel = MockChannel::new(b"", 8192);
+        let mut transport = ContentLengthTransport::new(channel);
+
+        futures_lite::future::block_on(transport.write_message(b"hi")).unwrap();
+
+        assert_eq!(
+            transport.into_inner().written,
+            b"Content-Length: 2\r\n\r\nhi".to_vec()
+        );
+    }
+}
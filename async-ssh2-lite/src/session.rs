@@ -23,6 +23,15 @@ pub struct AsyncSession<S> {
     stream: Arc<S>,
 }
 
+impl<S> Clone for AsyncSession<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            stream: self.stream.clone(),
+        }
+    }
+}
+
 #[cfg(unix)]
 impl<S> AsyncSession<S>
 where
@@ -114,9 +123,8 @@ where
         let mut agent = self.agent()?;
         agent.connect().await?;
         agent.list_identities().await?;
-        let identities = agent.identities()?;
-        let identity = match identities.get(0) {
-            Some(identity) => identity,
+        let identity = match agent.identities().first() {
+            Some(identity) => identity.clone(),
             None => {
                 return Err(io::Error::new(
                     io::ErrorKind::Other,
@@ -124,7 +132,7 @@ where
                 ))
             }
         };
-        agent.userauth(username, identity).await
+        agent.userauth(username, &identity).await
     }
 
     pub async fn userauth_pubkey_file(
@@ -218,8 +226,7 @@ where
     pub fn agent(&self) -> io::Result<AsyncAgent<S>> {
         let ret = self.inner.agent();
 
-        // ret.map(|agent| AsyncAgent::from_parts(agent, self.stream.clone()))
-        todo!()
+        ret.map(|agent| AsyncAgent::from_parts(agent, self.inner.clone(), self.stream.clone()))
     }
 
     pub fn known_hosts(&self) -> io::Result<KnownHosts> {
@@ -232,8 +239,7 @@ where
             .read_and_write_with(&self.inner, || self.inner.channel_session())
             .await;
 
-        // ret.map(|channel| AsyncChannel::from_parts(channel, self.stream.clone()))
-        todo!()
+        ret.map(|channel| AsyncChannel::from_parts(channel, self.inner.clone(), self.stream.clone()))
     }
 
     pub async fn channel_direct_tcpip(
@@ -249,8 +255,7 @@ where
             })
             .await;
 
-        // ret.map(|channel| AsyncChannel::from_parts(channel, self.stream.clone()))
-        todo!()
+        ret.map(|channel| AsyncChannel::from_parts(channel, self.inner.clone(), self.stream.clone()))
     }
 
     pub async fn channel_forward_listen(
@@ -268,13 +273,12 @@ where
             })
             .await;
 
-        // ret.map(|(listener, port)| {
-        //     (
-        //         AsyncListener::from_parts(listener, self.stream.clone()),
-        //         port,
-        //     )
-        // })
-        todo!()
+        ret.map(|(listener, port)| {
+            (
+                AsyncListener::from_parts(listener, self.stream.clone()),
+                port,
+            )
+        })
     }
 
     pub async fn scp_recv(&self, path: &Path) -> io::Result<(AsyncChannel<S>, ScpFileStat)> {
@@ -381,37 +385,35 @@ impl<S> AsyncSession<S> {
     }
 
     pub async fn userauth_agent_with_try_next(&self, username: &str) -> io::Result<()> {
-        // let mut agent = self.agent()?;
-        // agent.connect().await?;
-        // agent.list_identities().await?;
-        // let identities = agent.identities()?;
-
-        // if identities.is_empty() {
-        //     return Err(io::Error::new(
-        //         io::ErrorKind::Other,
-        //         "no identities found in the ssh agent",
-        //     ));
-        // }
-
-        // for identity in identities {
-        //     match agent.userauth(username, &identity).await {
-        //         Ok(_) => {
-        //             if self.authenticated() {
-        //                 return Ok(());
-        //             }
-        //         }
-        //         Err(_) => {
-        //             continue;
-        //         }
-        //     }
-        // }
-
-        // Err(io::Error::new(
-        //     io::ErrorKind::Other,
-        //     "all identities cannot authenticated",
-        // ))
+        let mut agent = self.agent()?;
+        agent.connect().await?;
+        agent.list_identities().await?;
+        let identities = agent.identities().to_vec();
 
-        todo!()
+        if identities.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "no identities found in the ssh agent",
+            ));
+        }
+
+        for identity in &identities {
+            match agent.userauth(username, identity).await {
+                Ok(_) => {
+                    if self.authenticated() {
+                        return Ok(());
+                    }
+                }
+                Err(_) => {
+                    continue;
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "all identities failed to authenticate",
+        ))
     }
 }
 